@@ -12,16 +12,42 @@ use std::env;
 use bs58;
 use dotenv::dotenv;
 use base64::{decode as base64_decode, encode as base64_encode};
-use chrono;
 use serde::{Deserialize, Serialize};
 use reqwest::Client;
 use std::str::FromStr;
 use bincode;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use async_trait::async_trait;
+use futures::future::join_all;
+use tracing::{debug, error, info, warn};
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_gauge_vec, register_histogram_vec, register_int_counter, register_int_gauge,
+    Encoder, GaugeVec, HistogramVec, IntCounter, IntGauge, TextEncoder,
+};
+use tokio::net::TcpListener;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
 // Common token definitions
 const USDC_MINT: Pubkey = pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
 const NATIVE_MINT: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
 
+/// True when the bot should simulate fills instead of signing/broadcasting
+/// real transactions, via `--dry-run` or `MOCK_EXECUTION=1`. Lets users
+/// validate a stop-loss/take-profit config against live quotes with zero
+/// on-chain risk.
+fn is_mock_execution() -> bool {
+    if env::args().any(|arg| arg == "--dry-run") {
+        return true;
+    }
+    env::var("MOCK_EXECUTION")
+        .map(|v| matches!(v.trim(), "1" | "true" | "True" | "TRUE"))
+        .unwrap_or(false)
+}
+
 // Configuration struct for dynamic parameters
 struct TradingConfig {
     token_mint: Pubkey,          // SPL token to trade
@@ -29,9 +55,13 @@ struct TradingConfig {
     stop_loss_percentage: f64,   // Stop loss as a percentage (e.g., 0.2 = 20% loss)
     take_profit_percentage: f64, // Take profit as a percentage (e.g., 0.2 = 20% gain)
     slippage_bps: u16,           // Slippage in basis points (e.g., 100 = 1%)
+    trailing_stop_percentage: Option<f64>, // Retrace from the best price seen that triggers a sell, if set
+    slippage_buffer_percentage: f64,       // Assume the executable price is this much worse than the quote
+    execution_threshold_lamports: u64,     // Don't fire a sell whose expected SOL output is below this
 }
 
 // New struct to track purchases
+#[derive(Serialize, Deserialize, Clone, Debug)]
 struct TokenPurchase {
     token_mint: Pubkey,
     purchase_amount: u64,
@@ -39,6 +69,94 @@ struct TokenPurchase {
     target_price: f64,
     take_profit_price: f64,
     stop_loss_price: f64,
+    slippage_bps: u16,
+    // Best (lowest tokens-per-SOL, i.e. highest token value) price seen
+    // since purchase, used to drive the trailing stop below.
+    best_price: f64,
+    trailing_stop_percentage: Option<f64>,
+    slippage_buffer_percentage: f64,
+    execution_threshold_lamports: u64,
+}
+
+/// One entry in a multi-position portfolio config file: everything needed
+/// to open and then monitor a single position, the per-position analogue
+/// of `TradingConfig`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct PositionConfig {
+    token_mint: String,
+    sol_amount: f64,
+    stop_loss_percentage: f64,
+    take_profit_percentage: f64,
+    slippage_bps: u16,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct PortfolioConfig {
+    positions: Vec<PositionConfig>,
+}
+
+impl PositionConfig {
+    fn into_trading_config(self) -> Result<TradingConfig, Box<dyn std::error::Error>> {
+        let token_mint = Pubkey::from_str(&self.token_mint)
+            .map_err(|_| format!("Invalid token mint address: {}", self.token_mint))?;
+        let (trailing_stop_percentage, slippage_buffer_percentage, execution_threshold_lamports) =
+            load_risk_params_from_env()?;
+        Ok(TradingConfig {
+            token_mint,
+            sol_amount: (self.sol_amount * 1e9) as u64,
+            stop_loss_percentage: self.stop_loss_percentage,
+            take_profit_percentage: self.take_profit_percentage,
+            slippage_bps: self.slippage_bps,
+            trailing_stop_percentage,
+            slippage_buffer_percentage,
+            execution_threshold_lamports,
+        })
+    }
+}
+
+/// Risk-management knobs that apply uniformly across every position
+/// (trailing stop, slippage buffer, execution dust threshold), shared by
+/// both `load_config_from_env` and `PositionConfig::into_trading_config`.
+fn load_risk_params_from_env() -> Result<(Option<f64>, f64, u64), Box<dyn std::error::Error>> {
+    let trailing_stop_percentage = match env::var("TRAILING_STOP_PERCENTAGE") {
+        Ok(v) => Some(v.parse::<f64>().map_err(|_| format!("Invalid trailing stop percentage: {}", v))?),
+        Err(_) => None,
+    };
+
+    // Default 1%: assume the executable price is ~1% worse than the quote
+    // so a threshold crossing is unlikely to fail at submission.
+    let slippage_buffer_str = env::var("SLIPPAGE_BUFFER").unwrap_or_else(|_| "0.01".to_string());
+    let slippage_buffer_percentage: f64 = slippage_buffer_str.parse()
+        .map_err(|_| format!("Invalid slippage buffer: {}", slippage_buffer_str))?;
+
+    // Default 0.001 SOL: don't fire a sell whose expected output is dust.
+    let execution_threshold_str = env::var("EXECUTION_THRESHOLD").unwrap_or_else(|_| "1000000".to_string());
+    let execution_threshold_lamports: u64 = execution_threshold_str.parse()
+        .map_err(|_| format!("Invalid execution threshold: {}", execution_threshold_str))?;
+
+    Ok((trailing_stop_percentage, slippage_buffer_percentage, execution_threshold_lamports))
+}
+
+/// Loads a multi-position config from `path` if it exists, otherwise falls
+/// back to a single position built from the legacy `TOKEN_MINT`/`SOL_AMOUNT`/
+/// etc. environment variables so existing single-token setups keep working.
+fn load_portfolio_config(path: &str) -> Result<PortfolioConfig, Box<dyn std::error::Error>> {
+    match std::fs::read_to_string(path) {
+        Ok(data) => Ok(serde_json::from_str(&data)?),
+        Err(_) => {
+            println!("No portfolio config at {}, falling back to single-position env config", path);
+            let config = load_config_from_env()?;
+            Ok(PortfolioConfig {
+                positions: vec![PositionConfig {
+                    token_mint: config.token_mint.to_string(),
+                    sol_amount: config.sol_amount as f64 / 1e9,
+                    stop_loss_percentage: config.stop_loss_percentage,
+                    take_profit_percentage: config.take_profit_percentage,
+                    slippage_bps: config.slippage_bps,
+                }],
+            })
+        }
+    }
 }
 
 #[derive(Serialize, Debug)]
@@ -63,6 +181,10 @@ pub struct OrderResponse {
     pub price_impact_pct: String,
     pub transaction: Option<String>,
     pub request_id: String,
+    // Not part of Jupiter's Ultra response; other providers populate this so
+    // best_quote() can compare routes net of fees instead of raw out_amount.
+    #[serde(default)]
+    pub fee_bps: Option<u16>,
 }
 
 #[derive(Serialize, Debug)]
@@ -79,22 +201,448 @@ pub struct ExecuteResponse {
     pub extra: std::collections::HashMap<String, serde_json::Value>,
 }
 
+// How long a cached quote is considered fresh enough to skip a round-trip.
+const QUOTE_CACHE_TTL: Duration = Duration::from_millis(750);
+
+// Prometheus metrics, registered lazily against the default registry so
+// `serve_metrics` can scrape them without any extra wiring at the call sites.
+static QUOTE_REQUESTS_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!("jupswaps_quote_requests_total", "Total quote requests made to swap providers").unwrap()
+});
+static QUOTE_ERRORS_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!("jupswaps_quote_errors_total", "Total quote requests that returned an error").unwrap()
+});
+static OPEN_POSITIONS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!("jupswaps_open_positions", "Number of currently open positions").unwrap()
+});
+static CURRENT_PRICE: Lazy<GaugeVec> = Lazy::new(|| {
+    register_gauge_vec!(
+        "jupswaps_current_price_tokens_per_sol",
+        "Current quoted price in tokens per SOL",
+        &["mint"]
+    )
+    .unwrap()
+});
+static UNREALIZED_PNL_PERCENT: Lazy<GaugeVec> = Lazy::new(|| {
+    register_gauge_vec!(
+        "jupswaps_unrealized_pnl_percent",
+        "Unrealized P/L percent versus purchase price",
+        &["mint"]
+    )
+    .unwrap()
+});
+static EXECUTE_LATENCY_SECONDS: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "jupswaps_execute_latency_seconds",
+        "Latency of provider execute() calls",
+        &["provider"]
+    )
+    .unwrap()
+});
+
+/// Serves `/metrics` in Prometheus text format on `port`. Spawned once from
+/// `main` and left running for the life of the process; any other path gets
+/// a 404. Kept as a raw TCP listener instead of pulling in a web framework
+/// since this is the only route the bot needs to expose.
+async fn serve_metrics(port: u16) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind(("0.0.0.0", port)).await?;
+    info!(port, "metrics endpoint listening on /metrics");
+
+    loop {
+        let (mut socket, _) = listener.accept().await?;
+        tokio::spawn(async move {
+            let mut buf = [0u8; 1024];
+            let n = match socket.read(&mut buf).await {
+                Ok(n) => n,
+                Err(_) => return,
+            };
+
+            let request_line = String::from_utf8_lossy(&buf[..n]);
+            let path = request_line
+                .lines()
+                .next()
+                .and_then(|line| line.split_whitespace().nth(1))
+                .unwrap_or("/");
+
+            if path != "/metrics" {
+                let response = "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n";
+                let _ = socket.write_all(response.as_bytes()).await;
+                return;
+            }
+
+            let metric_families = prometheus::gather();
+            let mut body = Vec::new();
+            let encoder = TextEncoder::new();
+            if encoder.encode(&metric_families, &mut body).is_err() {
+                return;
+            }
+
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+                encoder.format_type(),
+                body.len()
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+            let _ = socket.write_all(&body).await;
+        });
+    }
+}
+
+/// Outcome of a `JupiterQuoteCache::cached_price` lookup.
+///
+/// `Fresh` and `Cached` both carry a usable price; `BadPrice` means the
+/// refresh attempt failed and the value is a stale fallback that callers
+/// should treat with caution (e.g. skip firing a sell on it).
+#[derive(Debug, Clone, Copy)]
+pub enum QuoteResult {
+    Fresh(f64),
+    Cached(f64),
+    BadPrice(f64),
+}
+
+impl QuoteResult {
+    pub fn price(&self) -> f64 {
+        match self {
+            QuoteResult::Fresh(p) | QuoteResult::Cached(p) | QuoteResult::BadPrice(p) => *p,
+        }
+    }
+}
+
+struct CachedQuote {
+    price: f64,
+    fetched_at: Instant,
+}
+
+/// Memoizes the best quote across the configured swap providers per
+/// `(input_mint, output_mint)` pair so a tight polling loop (e.g.
+/// `PortfolioManager::run_cycle`) doesn't hammer them once per tick per
+/// position.
+///
+/// Each pair gets its own `tokio::sync::Mutex`. The very first quote for a
+/// pair is fetched while holding that mutex, so concurrent callers for a
+/// brand-new pair queue up behind the same request instead of firing
+/// duplicate ones. Once a pair has an initial price, refreshes are done
+/// without holding the lock across the await, so multiple in-flight
+/// refreshes for the same pair can run in parallel.
+pub struct JupiterQuoteCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<(String, String), Arc<Mutex<Option<CachedQuote>>>>>,
+}
+
+impl JupiterQuoteCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn entry_for(&self, input_mint: &str, output_mint: &str) -> Arc<Mutex<Option<CachedQuote>>> {
+        let mut entries = self.entries.lock().await;
+        entries
+            .entry((input_mint.to_string(), output_mint.to_string()))
+            .or_insert_with(|| Arc::new(Mutex::new(None)))
+            .clone()
+    }
+
+    async fn fetch_price(
+        providers: &[Box<dyn SwapProvider>],
+        input_mint: &str,
+        output_mint: &str,
+        amount: u64,
+    ) -> Result<f64, Box<dyn std::error::Error>> {
+        let order_request = OrderRequest {
+            amount: amount.to_string(),
+            input_mint: input_mint.to_string(),
+            output_mint: output_mint.to_string(),
+            slippage_bps: None,
+            taker: None,
+        };
+        let (order_response, _provider_idx) = best_quote(providers, &order_request).await?;
+        let out_amount = order_response.out_amount.parse::<f64>()?;
+        // Price is expressed input-per-output in raw units (e.g. tokens per
+        // lamport when selling tokens back to SOL); evaluate_position scales
+        // it to the tokens-per-SOL convention of buy_token_with_sol.
+        Ok(amount as f64 / out_amount)
+    }
+
+    /// Returns the cached price for the `(input_mint, output_mint)` pair if
+    /// it's within the TTL (a cheap early-out, no API call), otherwise
+    /// triggers a refresh for `amount` across all configured `providers`.
+    /// `amount` is not part of the cache key.
+    pub async fn cached_price(
+        &self,
+        providers: &[Box<dyn SwapProvider>],
+        input_mint: &str,
+        output_mint: &str,
+        amount: u64,
+    ) -> Result<QuoteResult, Box<dyn std::error::Error>> {
+        let entry = self.entry_for(input_mint, output_mint).await;
+        let mut guard = entry.lock().await;
+
+        if guard.is_none() {
+            // First quote for this pair: fetch while holding the lock so
+            // concurrent first-callers wait for this one instead of all
+            // firing their own requests.
+            let price = Self::fetch_price(providers, input_mint, output_mint, amount).await?;
+            *guard = Some(CachedQuote {
+                price,
+                fetched_at: Instant::now(),
+            });
+            return Ok(QuoteResult::Fresh(price));
+        }
+
+        let cached = guard.as_ref().unwrap();
+        if cached.fetched_at.elapsed() < self.ttl {
+            return Ok(QuoteResult::Cached(cached.price));
+        }
+
+        let stale_price = cached.price;
+        // Drop the lock before the round-trip so other callers can still
+        // read (or refresh) this pair while this refresh is in flight.
+        drop(guard);
+
+        match Self::fetch_price(providers, input_mint, output_mint, amount).await {
+            Ok(price) => {
+                let mut guard = entry.lock().await;
+                *guard = Some(CachedQuote {
+                    price,
+                    fetched_at: Instant::now(),
+                });
+                Ok(QuoteResult::Fresh(price))
+            }
+            Err(e) => {
+                println!("Quote refresh failed for {}->{}: {}", input_mint, output_mint, e);
+                Ok(QuoteResult::BadPrice(stale_price))
+            }
+        }
+    }
+}
+
+/// A source of swap routes. `order()`/`execute()` used to be hardwired to
+/// Jupiter's Ultra API; implementing this trait lets the bot shop a quote
+/// across multiple routers and fall back when one is down or offers a
+/// worse fill for a given mint.
+#[async_trait]
+pub trait SwapProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn quote(&self, request: &OrderRequest) -> Result<OrderResponse, Box<dyn std::error::Error>>;
+    async fn execute(&self, request: &ExecuteRequest) -> Result<serde_json::Value, Box<dyn std::error::Error>>;
+}
+
+/// Net output amount after the provider's reported fee, used to rank quotes
+/// from different providers against each other.
+fn net_out_amount(response: &OrderResponse) -> f64 {
+    let out_amount = response.out_amount.parse::<f64>().unwrap_or(0.0);
+    let fee_bps = response.fee_bps.unwrap_or(0) as f64;
+    out_amount * (1.0 - fee_bps / 10_000.0)
+}
+
+/// Requests a quote from every provider concurrently and returns the one
+/// with the best fee-adjusted `out_amount`, alongside the index of the
+/// provider that produced it (needed so the caller can `execute()` against
+/// the same provider). Providers that error out are logged and skipped; if
+/// all of them fail, a generic "all providers failed" error is returned.
+async fn best_quote(
+    providers: &[Box<dyn SwapProvider>],
+    request: &OrderRequest,
+) -> Result<(OrderResponse, usize), Box<dyn std::error::Error>> {
+    QUOTE_REQUESTS_TOTAL.inc_by(providers.len() as u64);
+    let quotes = join_all(providers.iter().map(|p| p.quote(request))).await;
+
+    let mut best: Option<(OrderResponse, usize)> = None;
+    for (idx, result) in quotes.into_iter().enumerate() {
+        match result {
+            Ok(response) => {
+                let is_better = match &best {
+                    Some((current, _)) => net_out_amount(&response) > net_out_amount(current),
+                    None => true,
+                };
+                if is_better {
+                    best = Some((response, idx));
+                }
+            }
+            Err(e) => {
+                QUOTE_ERRORS_TOTAL.inc();
+                warn!(provider = providers[idx].name(), error = %e, "quote failed, trying the rest");
+            }
+        }
+    }
+
+    best.ok_or_else(|| "All swap providers failed to return a quote".into())
+}
+
+/// Jupiter's Ultra API, wired through the existing `order()`/`execute()`
+/// helpers below.
+pub struct JupiterProvider {
+    client: JupiterSwapApiClient,
+}
+
+impl JupiterProvider {
+    pub fn new(client: JupiterSwapApiClient) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl SwapProvider for JupiterProvider {
+    fn name(&self) -> &'static str {
+        "jupiter"
+    }
+
+    async fn quote(&self, request: &OrderRequest) -> Result<OrderResponse, Box<dyn std::error::Error>> {
+        order(&self.client, request).await
+    }
+
+    async fn execute(&self, request: &ExecuteRequest) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        execute(&self.client, request).await
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SanctumQuoteRequest<'a> {
+    input: &'a str,
+    output_lst: &'a str,
+    amount: &'a str,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SanctumQuoteResponse {
+    out_amount: String,
+    #[serde(default)]
+    fee_bps: Option<u16>,
+    #[serde(default)]
+    swap_transaction: Option<String>,
+    #[serde(default)]
+    quote_id: Option<String>,
+}
+
+/// Sanctum's LST router. Best suited for liquid-staking-token pairs (e.g.
+/// mSOL, jitoSOL) where it tends to route more efficiently than a general
+/// aggregator.
+pub struct SanctumProvider {
+    http: Client,
+    base_url: String,
+}
+
+impl SanctumProvider {
+    pub fn new() -> Self {
+        Self {
+            http: Client::new(),
+            base_url: "https://api.sanctum.so/v1".to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl SwapProvider for SanctumProvider {
+    fn name(&self) -> &'static str {
+        "sanctum"
+    }
+
+    async fn quote(&self, request: &OrderRequest) -> Result<OrderResponse, Box<dyn std::error::Error>> {
+        let sanctum_request = SanctumQuoteRequest {
+            input: &request.input_mint,
+            output_lst: &request.output_mint,
+            amount: &request.amount,
+        };
+
+        let response = self
+            .http
+            .get(format!("{}/route/quote", self.base_url))
+            .query(&sanctum_request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Sanctum request failed with status: {}", response.status()).into());
+        }
+
+        let sanctum_response = response.json::<SanctumQuoteResponse>().await?;
+        Ok(OrderResponse {
+            input_mint: request.input_mint.clone(),
+            output_mint: request.output_mint.clone(),
+            in_amount: request.amount.clone(),
+            out_amount: sanctum_response.out_amount,
+            price_impact_pct: "0".to_string(),
+            transaction: sanctum_response.swap_transaction,
+            request_id: sanctum_response.quote_id.unwrap_or_default(),
+            fee_bps: sanctum_response.fee_bps,
+        })
+    }
+
+    async fn execute(&self, request: &ExecuteRequest) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let response = self
+            .http
+            .post(format!("{}/route/execute", self.base_url))
+            .json(request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Sanctum execute failed with status: {}", response.status()).into());
+        }
+
+        Ok(response.json::<serde_json::Value>().await?)
+    }
+}
+
+// A third "generic REST aggregator modeled on 0x's swap quote API" route
+// used to live here. It's been pulled out: 0x's public API
+// (https://api.0x.org/swap/v1) is EVM-only and doesn't understand Solana
+// base58 mints, so every live call against it would fail or return
+// nonsense, and its `execute()` had no way to actually settle a trade
+// anyway. Re-add a third provider once there's a real Solana-compatible
+// aggregator to implement `SwapProvider` against.
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Human-readable console subscriber by default; set RUST_LOG to adjust verbosity.
+    tracing_subscriber::fmt::init();
+
     // Load .env file if it exists
     dotenv().ok();
 
-    // Load configuration from environment variables or use defaults
-    let config = load_config_from_env()?;
-    
-    // Display config at startup
-    println!("\n===== TRADING CONFIGURATION =====");
-    println!("Token Mint: {}", config.token_mint);
-    println!("SOL Amount: {} SOL", config.sol_amount as f64 / 1e9);
-    println!("Stop Loss: {}%", config.stop_loss_percentage * 100.0);
-    println!("Take Profit: {}%", config.take_profit_percentage * 100.0);
-    println!("Slippage: {}%", config.slippage_bps as f64 / 100.0);
-    println!("=================================\n");
+    let metrics_port: u16 = env::var("METRICS_PORT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(9898);
+    tokio::spawn(async move {
+        if let Err(e) = serve_metrics(metrics_port).await {
+            error!(error = %e, "metrics server exited");
+        }
+    });
+
+    // Load a multi-position portfolio config, falling back to the legacy
+    // single-token env vars if no portfolio file is present.
+    let portfolio_config_path = env::var("PORTFOLIO_CONFIG_PATH").unwrap_or_else(|_| "portfolio.json".to_string());
+    let portfolio_config = load_portfolio_config(&portfolio_config_path)?;
+
+    println!("\n===== PORTFOLIO CONFIGURATION =====");
+    for position in &portfolio_config.positions {
+        println!(
+            "{}: {} SOL, stop-loss {}%, take-profit {}%, slippage {}%",
+            position.token_mint,
+            position.sol_amount,
+            position.stop_loss_percentage * 100.0,
+            position.take_profit_percentage * 100.0,
+            position.slippage_bps as f64 / 100.0,
+        );
+    }
+    println!("====================================\n");
+
+    let (trailing_stop_percentage, slippage_buffer_percentage, execution_threshold_lamports) =
+        load_risk_params_from_env()?;
+    println!("\n===== RISK MANAGEMENT =====");
+    match trailing_stop_percentage {
+        Some(pct) => println!("Trailing stop: {}% retrace from best price", pct * 100.0),
+        None => println!("Trailing stop: disabled"),
+    }
+    println!("Slippage buffer: {}%", slippage_buffer_percentage * 100.0);
+    println!("Execution threshold: {} lamports ({} SOL)", execution_threshold_lamports, execution_threshold_lamports as f64 / 1e9);
+    println!("============================\n");
 
     // Setup wallet from private key
     let private_key = env::var("SOLANA_PRIVATE_KEY")
@@ -112,7 +660,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Loaded wallet address: {}", loaded_pubkey);
     
     // Initialize clients
-    let jupiter_client = JupiterSwapApiClient::new("https://quote-api.jup.ag/v6".to_string());
+    let providers: Vec<Box<dyn SwapProvider>> = vec![
+        Box::new(JupiterProvider::new(JupiterSwapApiClient::new("https://quote-api.jup.ag/v6".to_string()))),
+        Box::new(SanctumProvider::new()),
+    ];
     let rpc_client = RpcClient::new("https://api.mainnet-beta.solana.com".to_string());
     
     // Check SOL balance
@@ -123,24 +674,46 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Err("Insufficient SOL for transaction fees. Please ensure you have at least 0.01 SOL".into());
     }
 
-    // Check if we have enough SOL for the trade
-    if sol_balance < config.sol_amount + 10_000_000 { // Adding 0.01 SOL for fees
-        return Err(format!(
-            "Insufficient SOL for trade. Need {} SOL but only have {} SOL", 
-            (config.sol_amount + 10_000_000) as f64 / 1e9, 
-            sol_balance as f64 / 1e9
-        ).into());
-    }
+    // Resume any positions left open from a previous run so we don't re-buy them.
+    let positions_state_path = env::var("POSITIONS_STATE_PATH").unwrap_or_else(|_| "positions.json".to_string());
+    let execution_budget: usize = env::var("EXECUTION_BUDGET_PER_CYCLE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(3);
+    let portfolio = PortfolioManager::new(positions_state_path, execution_budget);
+    portfolio.load_from_disk().await?;
 
-    // Automatically buy and monitor
+    // Buy whichever configured positions aren't already open
     println!("Starting automated buy and sell process...");
-    
-    // Buy tokens using SOL
-    let purchase = buy_token_with_sol(&jupiter_client, &rpc_client, &keypair, &config).await?;
-    
-    // Start monitoring for sell conditions
-    monitor_price_and_sell(&jupiter_client, &rpc_client, &keypair, purchase, config.slippage_bps).await?;
-    
+    for position in &portfolio_config.positions {
+        let trading_config = position.clone().into_trading_config()?;
+        if portfolio.is_open(&trading_config.token_mint).await {
+            println!("{} already open, resuming monitoring", trading_config.token_mint);
+            continue;
+        }
+
+        let needed = trading_config.sol_amount + 10_000_000; // plus fees
+        let sol_balance = rpc_client.get_balance(&keypair.pubkey()).await?;
+        if sol_balance < needed {
+            println!(
+                "Skipping {}: need {} SOL but only have {} SOL",
+                trading_config.token_mint,
+                needed as f64 / 1e9,
+                sol_balance as f64 / 1e9
+            );
+            continue;
+        }
+
+        let purchase = buy_token_with_sol(&providers, &rpc_client, &keypair, &trading_config).await?;
+        portfolio.add_position(purchase).await?;
+    }
+
+    // Drive every open position concurrently from a single polling loop
+    let check_interval = Duration::from_secs(1);
+    portfolio
+        .run(&providers, &rpc_client, &keypair, &portfolio_config_path, check_interval)
+        .await?;
+
     Ok(())
 }
 
@@ -173,24 +746,30 @@ fn load_config_from_env() -> Result<TradingConfig, Box<dyn std::error::Error>> {
     let slippage_str = env::var("SLIPPAGE_BPS").unwrap_or_else(|_| "100".to_string()); // Default to 1%
     let slippage_bps: u16 = slippage_str.parse()
         .map_err(|_| format!("Invalid slippage basis points: {}", slippage_str))?;
-    
+
+    let (trailing_stop_percentage, slippage_buffer_percentage, execution_threshold_lamports) =
+        load_risk_params_from_env()?;
+
     Ok(TradingConfig {
         token_mint,
         sol_amount,
         stop_loss_percentage,
         take_profit_percentage,
         slippage_bps,
+        trailing_stop_percentage,
+        slippage_buffer_percentage,
+        execution_threshold_lamports,
     })
 }
 
 // Modified buy function that uses the configuration
 async fn buy_token_with_sol(
-    jupiter_client: &JupiterSwapApiClient,
+    providers: &[Box<dyn SwapProvider>],
     rpc_client: &RpcClient,
     keypair: &Keypair,
     config: &TradingConfig,
 ) -> Result<TokenPurchase, Box<dyn std::error::Error>> {
-    // Create order for SOL â†’ Token using Ultra API
+    // Create order for SOL â†’ Token, shopped across all configured providers
     let order_request = OrderRequest {
         amount: config.sol_amount.to_string(),
         input_mint: NATIVE_MINT.to_string(),
@@ -198,201 +777,624 @@ async fn buy_token_with_sol(
         slippage_bps: Some(config.slippage_bps),
         taker: Some(keypair.pubkey().to_string()),
     };
-    
-    println!("\nGetting quote for {} SOL to token...", config.sol_amount as f64 / 1e9);
-    let order_response = order(jupiter_client, &order_request).await?;
-    
-    println!("\nQuote Details:");
-    println!("Input: {} SOL", order_response.in_amount.parse::<f64>().unwrap() / 1e9);
-    let token_amount = order_response.out_amount.parse::<f64>().unwrap();
-    println!("Output: {} tokens", token_amount);
-    println!("Price Impact: {}%", order_response.price_impact_pct);
 
-    println!("\nExecuting swap...");
-    
-    // Decode and sign the transaction
-    let tx_bytes = base64_decode(&order_response.transaction.clone().unwrap())?;
-    let transaction: VersionedTransaction = bincode::deserialize(&tx_bytes)?;
-    
-    let signed_transaction = VersionedTransaction::try_new(
-        transaction.message,
-        &[keypair]
-    )?;
-    
-    // Execute the transaction using Jupiter Ultra API
-    let execute_request = ExecuteRequest {
-        request_id: order_response.request_id.clone(),
-        signed_transaction: base64::encode(bincode::serialize(&signed_transaction)?),
-    };
-    
-    let execute_response = execute(jupiter_client, &execute_request).await?;
-    
-    // Extract the transaction signature
-    let signature = execute_response.get("txId")
-        .or_else(|| execute_response.get("signature"))
-        .or_else(|| execute_response.get("txSignature"))
-        .expect("Could not find transaction signature in response")
-        .as_str()
-        .expect("Transaction signature is not a string");
-    
-    println!("\nSwap successful!");
-    println!("Transaction signature: {}", signature);
-    println!("View on Solscan: https://solscan.io/tx/{}", signature);
+    info!(sol_amount = config.sol_amount as f64 / 1e9, "requesting quote for SOL to token");
+    let (order_response, provider_idx) = best_quote(providers, &order_request).await?;
+    let provider_name = providers[provider_idx].name();
+    // best_quote() hands us whichever provider's response looked best, so by
+    // this point it could be Jupiter's or a third party's (e.g. Sanctum) —
+    // parse defensively with `?` instead of panicking the whole bot on a
+    // malformed field from a route we don't control.
+    let token_amount = order_response.out_amount.parse::<f64>()?;
+    let input_sol = order_response.in_amount.parse::<f64>()? / 1e9;
+    info!(
+        provider = provider_name,
+        input_sol,
+        output_tokens = token_amount,
+        price_impact_pct = %order_response.price_impact_pct,
+        "best route selected"
+    );
+
+    let mock_mode = is_mock_execution();
+    if mock_mode {
+        // MOCK_EXECUTION / --dry-run: record the simulated fill from the
+        // quote's out_amount without signing or broadcasting anything.
+        info!("MOCK_EXECUTION: skipping transaction signing/broadcast; recording simulated fill");
+    } else {
+        // Decode and sign the transaction
+        let tx_bytes = base64_decode(&order_response.transaction.clone().unwrap())?;
+        let transaction: VersionedTransaction = bincode::deserialize(&tx_bytes)?;
+
+        let signed_transaction = VersionedTransaction::try_new(
+            transaction.message,
+            &[keypair]
+        )?;
+
+        // Execute the transaction against whichever provider won the quote
+        let execute_request = ExecuteRequest {
+            request_id: order_response.request_id.clone(),
+            signed_transaction: base64::encode(bincode::serialize(&signed_transaction)?),
+        };
+
+        let execute_timer = Instant::now();
+        let execute_response = providers[provider_idx].execute(&execute_request).await?;
+        EXECUTE_LATENCY_SECONDS
+            .with_label_values(&[provider_name])
+            .observe(execute_timer.elapsed().as_secs_f64());
+
+        // Extract the transaction signature
+        let signature = execute_response.get("txId")
+            .or_else(|| execute_response.get("signature"))
+            .or_else(|| execute_response.get("txSignature"))
+            .expect("Could not find transaction signature in response")
+            .as_str()
+            .expect("Transaction signature is not a string");
+
+        info!(signature, "swap successful");
+
+        // Check final balances
+        let final_sol_balance = rpc_client.get_balance(&keypair.pubkey()).await?;
+        info!(final_sol_balance = final_sol_balance as f64 / 1e9, "final SOL balance");
+    }
 
-    // Check final balances
-    let final_sol_balance = rpc_client.get_balance(&keypair.pubkey()).await?;
-    println!("\nFinal SOL balance: {} SOL", final_sol_balance as f64 / 1e9);
-    
     // Calculate purchase price (SOL per token)
-    let purchase_amount_in_sol = order_response.in_amount.parse::<f64>().unwrap() / 1e9;
-    let purchase_price = token_amount / purchase_amount_in_sol; // Tokens per SOL
-    
+    let purchase_price = token_amount / input_sol; // Tokens per SOL
+
     // Calculate take profit price (lower tokens per SOL = higher token value)
     let take_profit_price = purchase_price * (1.0 - config.take_profit_percentage);
-    
+
     // Calculate stop loss price (higher tokens per SOL = lower token value)
     let stop_loss_price = purchase_price * (1.0 + config.stop_loss_percentage);
 
-    println!("\n===== PURCHASE SUMMARY =====");
-    println!("Bought: {} tokens", token_amount);
-    println!("Paid: {} SOL", purchase_amount_in_sol);
-    println!("Purchase price: {} tokens per SOL", purchase_price);
-    println!("Take profit target: {} tokens per SOL (-{}%)", 
-             take_profit_price, config.take_profit_percentage * 100.0);
-    println!("Stop loss set at: {} tokens per SOL (+{}%)", 
-             stop_loss_price, config.stop_loss_percentage * 100.0);
-    println!("============================\n");
+    info!(
+        tokens_bought = token_amount,
+        sol_paid = input_sol,
+        purchase_price,
+        take_profit_price,
+        stop_loss_price,
+        "purchase summary"
+    );
 
     // Return purchase info
     Ok(TokenPurchase {
         token_mint: config.token_mint,
-        purchase_amount: order_response.out_amount.parse::<u64>().unwrap(),
+        purchase_amount: order_response.out_amount.parse::<u64>()?,
         purchase_price,
         target_price: purchase_price,
         take_profit_price,
         stop_loss_price,
+        slippage_bps: config.slippage_bps,
+        best_price: purchase_price,
+        trailing_stop_percentage: config.trailing_stop_percentage,
+        slippage_buffer_percentage: config.slippage_buffer_percentage,
+        execution_threshold_lamports: config.execution_threshold_lamports,
     })
 }
 
-// Modified function to monitor price and sell with configurable slippage
-async fn monitor_price_and_sell(
-    jupiter_client: &JupiterSwapApiClient,
-    _rpc_client: &RpcClient,
-    keypair: &Keypair, 
-    purchase: TokenPurchase,
-    slippage_bps: u16,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Use a shorter interval, e.g., 1 second
-    let check_interval = tokio::time::Duration::from_secs(1);
-    
-    println!("\n===== PRICE MONITORING STARTED =====");
-    println!("Original purchase price: {} tokens per SOL", purchase.purchase_price);
-    println!("Will TAKE PROFIT when price decreases below {} tokens/SOL", purchase.take_profit_price);
-    println!("OR will STOP LOSS when price increases above {} tokens per SOL", purchase.stop_loss_price);
-    println!("Using entire purchased amount ({} tokens) for price monitoring", purchase.purchase_amount);
-    println!("=====================================\n");
-    
-    loop {
-        // Create order request to check current price using entire purchased amount
-        let order_request = OrderRequest {
-            amount: purchase.purchase_amount.to_string(),
-            input_mint: purchase.token_mint.to_string(),
-            output_mint: NATIVE_MINT.to_string(),
-            slippage_bps: Some(slippage_bps),
-            taker: None,
+/// Result of pricing one open position against the current market.
+struct PositionEvaluation {
+    current_price: f64,
+    price_diff_pct: f64,
+    should_sell: bool,
+    sell_reason: &'static str,
+    // Updated best (lowest tokens-per-SOL) price seen so far; the caller
+    // writes this back into the stored position even when it doesn't sell.
+    new_best_price: f64,
+    expected_sol_out_lamports: u64,
+}
+
+/// Pure take-profit/trailing-stop/stop-loss decision for `purchase` given a
+/// `current_price` (tokens per SOL) already fetched by the caller. Split out
+/// of `evaluate_position` so the trailing-stop/slippage-buffer math can be
+/// unit tested without a network round-trip.
+fn evaluate_price(purchase: &TokenPurchase, current_price: f64) -> PositionEvaluation {
+    let token_amount = purchase.purchase_amount as f64;
+    let sol_amount = token_amount / current_price;
+    let expected_sol_out_lamports = (sol_amount * 1e9).max(0.0) as u64;
+
+    let original_value = token_amount / purchase.purchase_price;
+    let current_value = sol_amount;
+    let price_diff_pct = ((current_value / original_value) - 1.0) * 100.0;
+
+    // Lower tokens-per-SOL means the token is worth more, so the best price
+    // is the minimum observed.
+    let new_best_price = purchase.best_price.min(current_price);
+
+    // SLIPPAGE_BUFFER: assume the executable price will be a bit worse than
+    // the quote before checking whether a threshold is crossed, so we don't
+    // fire a sell whose quote is likely to fail at submission.
+    let buffered_price = current_price * (1.0 + purchase.slippage_buffer_percentage);
+
+    // Compared as a ratio so a retrace of exactly `pct` triggers; computing
+    // `new_best_price * (1.0 + pct)` can round just above the exact value.
+    let trailing_stop_triggered = purchase
+        .trailing_stop_percentage
+        .map(|pct| buffered_price / new_best_price >= 1.0 + pct)
+        .unwrap_or(false);
+
+    let should_sell = buffered_price <= purchase.take_profit_price
+        || trailing_stop_triggered
+        || buffered_price >= purchase.stop_loss_price;
+
+    let sell_reason = if buffered_price <= purchase.take_profit_price {
+        "TAKE PROFIT TARGET REACHED"
+    } else if trailing_stop_triggered {
+        "TRAILING STOP TRIGGERED"
+    } else if buffered_price >= purchase.stop_loss_price {
+        "STOP LOSS TRIGGERED"
+    } else {
+        "UNKNOWN"
+    };
+
+    PositionEvaluation {
+        current_price,
+        price_diff_pct,
+        should_sell,
+        sell_reason,
+        new_best_price,
+        expected_sol_out_lamports,
+    }
+}
+
+/// Evaluates one position against a fresh (or cached) quote and reports
+/// whether it should be sold this tick.
+async fn evaluate_position(
+    providers: &[Box<dyn SwapProvider>],
+    quote_cache: &JupiterQuoteCache,
+    purchase: &TokenPurchase,
+) -> Result<PositionEvaluation, Box<dyn std::error::Error>> {
+    let quote_result = quote_cache
+        .cached_price(
+            providers,
+            &purchase.token_mint.to_string(),
+            &NATIVE_MINT.to_string(),
+            purchase.purchase_amount,
+        )
+        .await?;
+
+    if let QuoteResult::BadPrice(_) = quote_result {
+        warn!(mint = %purchase.token_mint, "quote refresh failed, using stale cached price this tick");
+    }
+
+    // quote_cache prices are input-per-output in raw units (tokens per
+    // lamport here); scale to tokens per SOL to match purchase_price.
+    let current_price = quote_result.price() * 1e9;
+    let evaluation = evaluate_price(purchase, current_price);
+
+    let mint_label = purchase.token_mint.to_string();
+    CURRENT_PRICE.with_label_values(&[&mint_label]).set(evaluation.current_price);
+    UNREALIZED_PNL_PERCENT.with_label_values(&[&mint_label]).set(evaluation.price_diff_pct);
+
+    Ok(evaluation)
+}
+
+#[cfg(test)]
+mod evaluate_price_tests {
+    use super::*;
+
+    fn purchase(overrides: impl FnOnce(&mut TokenPurchase)) -> TokenPurchase {
+        let mut purchase = TokenPurchase {
+            token_mint: NATIVE_MINT,
+            purchase_amount: 1_000_000,
+            purchase_price: 100.0,
+            target_price: 100.0,
+            take_profit_price: 80.0,
+            stop_loss_price: 120.0,
+            slippage_bps: 100,
+            best_price: 100.0,
+            trailing_stop_percentage: None,
+            slippage_buffer_percentage: 0.0,
+            execution_threshold_lamports: 0,
         };
-        
-        // Get current price using Ultra API
-        let order_response = match order(jupiter_client, &order_request).await {
-            Ok(response) => response,
+        overrides(&mut purchase);
+        purchase
+    }
+
+    #[test]
+    fn take_profit_triggers_when_price_drops_to_target() {
+        let position = purchase(|_| {});
+        let evaluation = evaluate_price(&position, 80.0);
+        assert!(evaluation.should_sell);
+        assert_eq!(evaluation.sell_reason, "TAKE PROFIT TARGET REACHED");
+    }
+
+    #[test]
+    fn stop_loss_triggers_when_price_rises_to_target() {
+        let position = purchase(|_| {});
+        let evaluation = evaluate_price(&position, 120.0);
+        assert!(evaluation.should_sell);
+        assert_eq!(evaluation.sell_reason, "STOP LOSS TRIGGERED");
+    }
+
+    #[test]
+    fn no_sell_between_take_profit_and_stop_loss() {
+        let position = purchase(|_| {});
+        let evaluation = evaluate_price(&position, 100.0);
+        assert!(!evaluation.should_sell);
+    }
+
+    #[test]
+    fn trailing_stop_follows_best_price_then_triggers_on_retrace() {
+        let position = purchase(|p| p.trailing_stop_percentage = Some(0.1));
+
+        // Price improves to 90 (lower tokens-per-SOL = token worth more);
+        // best_price isn't updated in place, so feed the new best back in
+        // the way run_cycle's update_best_price would.
+        let improved = evaluate_price(&position, 90.0);
+        assert!(!improved.should_sell);
+        assert_eq!(improved.new_best_price, 90.0);
+
+        let mut trailing = position;
+        trailing.best_price = improved.new_best_price;
+
+        // A 10% retrace from the new best (90) trips the trailing stop
+        // well before the original stop-loss at 120 would.
+        let retraced = evaluate_price(&trailing, 99.0);
+        assert!(retraced.should_sell);
+        assert_eq!(retraced.sell_reason, "TRAILING STOP TRIGGERED");
+    }
+
+    #[test]
+    fn slippage_buffer_moves_the_effective_price_against_the_seller() {
+        // Raw price of 119 wouldn't trip the 120 stop-loss on its own, but a
+        // 1% slippage buffer bumps the effective price past it.
+        let position = purchase(|p| p.slippage_buffer_percentage = 0.01);
+        let evaluation = evaluate_price(&position, 119.0);
+        assert!(evaluation.should_sell);
+        assert_eq!(evaluation.sell_reason, "STOP LOSS TRIGGERED");
+    }
+}
+
+/// Sells an entire position back to SOL, shopping the exit across all
+/// configured providers the same way `buy_token_with_sol` shops the entry.
+async fn execute_sell(
+    providers: &[Box<dyn SwapProvider>],
+    keypair: &Keypair,
+    purchase: &TokenPurchase,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let sell_order_request = OrderRequest {
+        amount: purchase.purchase_amount.to_string(),
+        input_mint: purchase.token_mint.to_string(),
+        output_mint: NATIVE_MINT.to_string(),
+        slippage_bps: Some(purchase.slippage_bps),
+        taker: Some(keypair.pubkey().to_string()),
+    };
+
+    let (sell_order, provider_idx) = best_quote(providers, &sell_order_request).await?;
+    let provider_name = providers[provider_idx].name();
+    info!(mint = %purchase.token_mint, provider = provider_name, "selling position");
+
+    if is_mock_execution() {
+        // MOCK_EXECUTION / --dry-run: apply the configured slippage to the
+        // quoted out_amount and log the synthetic fill instead of calling
+        // the provider's execute() endpoint.
+        let quoted_out_amount = sell_order.out_amount.parse::<f64>().unwrap_or(0.0);
+        let slippage_factor = 1.0 - (purchase.slippage_bps as f64 / 10_000.0);
+        let simulated_out_lamports = quoted_out_amount * slippage_factor;
+
+        let original_value = purchase.purchase_amount as f64 / purchase.purchase_price;
+        let realized_value = simulated_out_lamports / 1e9;
+        let realized_pnl_pct = ((realized_value / original_value) - 1.0) * 100.0;
+
+        info!(
+            mint = %purchase.token_mint,
+            tokens_sold = purchase.purchase_amount,
+            realized_sol = realized_value,
+            slippage_bps = purchase.slippage_bps,
+            realized_pnl_pct,
+            "MOCK_EXECUTION: simulated sell"
+        );
+
+        return Ok(format!("MOCK-SELL-{}", purchase.token_mint));
+    }
+
+    let tx_bytes = base64_decode(&sell_order.transaction.unwrap())?;
+    let transaction: VersionedTransaction = bincode::deserialize(&tx_bytes)?;
+
+    let signed_transaction = VersionedTransaction::try_new(transaction.message, &[keypair])?;
+
+    let execute_request = ExecuteRequest {
+        request_id: sell_order.request_id,
+        signed_transaction: base64::encode(bincode::serialize(&signed_transaction)?),
+    };
+
+    let execute_timer = Instant::now();
+    let execute_response = providers[provider_idx].execute(&execute_request).await?;
+    EXECUTE_LATENCY_SECONDS
+        .with_label_values(&[provider_name])
+        .observe(execute_timer.elapsed().as_secs_f64());
+
+    let signature = execute_response.get("txId")
+        .or_else(|| execute_response.get("signature"))
+        .or_else(|| execute_response.get("txSignature"))
+        .expect("Could not find transaction signature in response")
+        .as_str()
+        .expect("Transaction signature is not a string");
+
+    Ok(signature.to_string())
+}
+
+/// Drives every open position concurrently from a single polling loop,
+/// instead of blocking on one `TokenPurchase` at a time. Positions can be
+/// added or removed at runtime and are persisted to disk as JSON so a
+/// restart resumes monitoring without re-buying.
+struct PortfolioManager {
+    positions: Mutex<HashMap<Pubkey, TokenPurchase>>,
+    quote_cache: JupiterQuoteCache,
+    persist_path: String,
+    // Max number of sells fired in a single polling cycle, so a simultaneous
+    // multi-position crash doesn't try to execute them all at once.
+    execution_budget: usize,
+}
+
+impl PortfolioManager {
+    fn new(persist_path: String, execution_budget: usize) -> Self {
+        Self {
+            positions: Mutex::new(HashMap::new()),
+            quote_cache: JupiterQuoteCache::new(QUOTE_CACHE_TTL),
+            persist_path,
+            execution_budget,
+        }
+    }
+
+    /// Loads previously-open positions from `persist_path`, if present.
+    async fn load_from_disk(&self) -> Result<(), Box<dyn std::error::Error>> {
+        match std::fs::read_to_string(&self.persist_path) {
+            Ok(data) => {
+                let loaded: HashMap<Pubkey, TokenPurchase> = serde_json::from_str(&data)?;
+                info!(count = loaded.len(), path = %self.persist_path, "resuming open positions from disk");
+                OPEN_POSITIONS.set(loaded.len() as i64);
+                *self.positions.lock().await = loaded;
+                Ok(())
+            }
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Writes the full positions map to `persist_path` as JSON. Goes
+    /// through `tokio::fs` rather than `std::fs` so the write doesn't block
+    /// the executor thread it runs on.
+    async fn persist(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let positions = self.positions.lock().await;
+        let data = serde_json::to_string_pretty(&*positions)?;
+        drop(positions);
+        tokio::fs::write(&self.persist_path, data).await?;
+        Ok(())
+    }
+
+    async fn is_open(&self, token_mint: &Pubkey) -> bool {
+        self.positions.lock().await.contains_key(token_mint)
+    }
+
+    async fn add_position(&self, purchase: TokenPurchase) -> Result<(), Box<dyn std::error::Error>> {
+        let mut positions = self.positions.lock().await;
+        positions.insert(purchase.token_mint, purchase);
+        OPEN_POSITIONS.set(positions.len() as i64);
+        drop(positions);
+        self.persist().await
+    }
+
+    /// Removes a position from memory only; callers inside `run_cycle`
+    /// persist once for the whole cycle instead of once per position.
+    async fn remove_position(&self, token_mint: &Pubkey) {
+        let mut positions = self.positions.lock().await;
+        positions.remove(token_mint);
+        OPEN_POSITIONS.set(positions.len() as i64);
+    }
+
+    /// Records a new best price for the trailing stop without touching
+    /// anything else about the position, and without persisting — callers
+    /// inside `run_cycle` persist once for the whole cycle instead of once
+    /// per position.
+    async fn update_best_price(&self, token_mint: &Pubkey, best_price: f64) {
+        if let Some(position) = self.positions.lock().await.get_mut(token_mint) {
+            position.best_price = best_price;
+        }
+    }
+
+    /// Drops monitoring for any open position whose mint is no longer in
+    /// `configured` — used by `reconcile_portfolio` when a position is
+    /// deleted from `portfolio.json` at runtime. This only stops tracking
+    /// the position; it does not sell it, since silently firing a live
+    /// trade off a config-file edit would be a surprising side effect.
+    async fn drop_unconfigured(&self, configured: &HashSet<Pubkey>) -> Result<(), Box<dyn std::error::Error>> {
+        let mut positions = self.positions.lock().await;
+        let before = positions.len();
+        positions.retain(|mint, _| {
+            let keep = configured.contains(mint);
+            if !keep {
+                info!(mint = %mint, "position removed from portfolio config; monitoring stopped (not sold)");
+            }
+            keep
+        });
+        let changed = positions.len() != before;
+        if changed {
+            OPEN_POSITIONS.set(positions.len() as i64);
+        }
+        drop(positions);
+        if changed {
+            self.persist().await?;
+        }
+        Ok(())
+    }
+
+    async fn is_empty(&self) -> bool {
+        self.positions.lock().await.is_empty()
+    }
+
+    /// Runs a single poll across every open position and executes up to
+    /// `execution_budget` of the sells it triggers.
+    async fn run_cycle(
+        &self,
+        providers: &[Box<dyn SwapProvider>],
+        keypair: &Keypair,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // Snapshot so we don't hold the positions lock across the quote awaits.
+        let snapshot: Vec<TokenPurchase> = self.positions.lock().await.values().cloned().collect();
+
+        let evaluations = join_all(snapshot.iter().map(|purchase| async move {
+            let result = evaluate_position(providers, &self.quote_cache, purchase).await;
+            (purchase.clone(), result)
+        }))
+        .await;
+
+        let mut sells_fired = 0;
+        let mut dirty = false;
+
+        for (purchase, result) in evaluations {
+            let evaluation = match result {
+                Ok(v) => v,
+                Err(e) => {
+                    error!(mint = %purchase.token_mint, error = %e, "error pricing position");
+                    continue;
+                }
+            };
+
+            debug!(
+                mint = %purchase.token_mint,
+                current_price = evaluation.current_price,
+                purchase_price = purchase.purchase_price,
+                price_diff_pct = evaluation.price_diff_pct,
+                "position priced"
+            );
+
+            if !evaluation.should_sell {
+                self.update_best_price(&purchase.token_mint, evaluation.new_best_price).await;
+                dirty = true;
+                continue;
+            }
+
+            if evaluation.expected_sol_out_lamports < purchase.execution_threshold_lamports {
+                info!(
+                    mint = %purchase.token_mint,
+                    expected_lamports = evaluation.expected_sol_out_lamports,
+                    threshold_lamports = purchase.execution_threshold_lamports,
+                    "expected output below EXECUTION_THRESHOLD, skipping this tick"
+                );
+                self.update_best_price(&purchase.token_mint, evaluation.new_best_price).await;
+                dirty = true;
+                continue;
+            }
+
+            if sells_fired >= self.execution_budget {
+                info!(
+                    mint = %purchase.token_mint,
+                    execution_budget = self.execution_budget,
+                    "execution budget reached this cycle, deferring sell"
+                );
+                self.update_best_price(&purchase.token_mint, evaluation.new_best_price).await;
+                dirty = true;
+                continue;
+            }
+
+            info!(
+                mint = %purchase.token_mint,
+                reason = evaluation.sell_reason,
+                current_price = evaluation.current_price,
+                purchase_price = purchase.purchase_price,
+                price_diff_pct = evaluation.price_diff_pct,
+                "sell triggered"
+            );
+
+            sells_fired += 1;
+            match execute_sell(providers, keypair, &purchase).await {
+                Ok(signature) => {
+                    info!(mint = %purchase.token_mint, signature, "sell complete");
+                    self.remove_position(&purchase.token_mint).await;
+                    dirty = true;
+                }
+                Err(e) => {
+                    error!(mint = %purchase.token_mint, error = %e, "sell failed");
+                    self.update_best_price(&purchase.token_mint, evaluation.new_best_price).await;
+                    dirty = true;
+                }
+            }
+        }
+
+        if dirty {
+            self.persist().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Polls all open positions until none remain, re-reading
+    /// `portfolio_config_path` each cycle so positions can be added or
+    /// removed at runtime by editing that file instead of restarting the
+    /// process (see `reconcile_portfolio`).
+    async fn run(
+        &self,
+        providers: &[Box<dyn SwapProvider>],
+        rpc_client: &RpcClient,
+        keypair: &Keypair,
+        portfolio_config_path: &str,
+        check_interval: Duration,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        loop {
+            reconcile_portfolio(self, providers, rpc_client, keypair, portfolio_config_path).await?;
+
+            if self.is_empty().await {
+                info!("no open positions left, stopping portfolio monitor");
+                break;
+            }
+            self.run_cycle(providers, keypair).await?;
+            tokio::time::sleep(check_interval).await;
+        }
+        Ok(())
+    }
+}
+
+/// Re-reads `config_path` and reconciles the in-memory portfolio against
+/// it: buys whichever positions are newly listed and stops monitoring
+/// (without selling) whichever open positions were deleted from the file.
+/// This is the runtime add/remove mechanism for `PortfolioManager::run` —
+/// positions are changed by editing `portfolio.json`, not by restarting.
+async fn reconcile_portfolio(
+    portfolio: &PortfolioManager,
+    providers: &[Box<dyn SwapProvider>],
+    rpc_client: &RpcClient,
+    keypair: &Keypair,
+    config_path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let config = match load_portfolio_config(config_path) {
+        Ok(config) => config,
+        Err(e) => {
+            warn!(path = config_path, error = %e, "failed to reload portfolio config, keeping current positions");
+            return Ok(());
+        }
+    };
+
+    let mut configured_mints = HashSet::new();
+    for position in &config.positions {
+        let trading_config = match position.clone().into_trading_config() {
+            Ok(trading_config) => trading_config,
             Err(e) => {
-                println!("Error getting price: {}", e);
-                tokio::time::sleep(check_interval).await;
+                warn!(mint = %position.token_mint, error = %e, "skipping invalid position in reloaded config");
                 continue;
             }
         };
-        
-        // Extract total SOL amount and calculate price per token
-        let sol_amount = order_response.out_amount.parse::<f64>().unwrap() / 1e9;
-        let token_amount = purchase.purchase_amount as f64;
-        let current_price = token_amount / sol_amount;  // Tokens per SOL
-        
-        // Compare current total value to original purchase value
-        let original_value = token_amount / purchase.purchase_price;
-        let current_value = sol_amount;
-        let price_diff_pct = ((current_value / original_value) - 1.0) * 100.0;
-        
-        let timestamp = chrono::Local::now().format("%H:%M:%S").to_string();
-        println!("[{}] Current: {} tokens/SOL | Purchase: {} tokens/SOL | Diff: {:+.6}%", 
-            timestamp, current_price, purchase.purchase_price, price_diff_pct);
-        
-        // Check if we should sell (take profit OR stop loss triggered)
-        let should_sell = current_price <= purchase.take_profit_price || current_price >= purchase.stop_loss_price;
-        let sell_reason = if current_price <= purchase.take_profit_price {
-            "TAKE PROFIT TARGET REACHED"
-        } else if current_price >= purchase.stop_loss_price {
-            "STOP LOSS TRIGGERED"
-        } else {
-            "UNKNOWN"
-        };
-        
-        if should_sell {
-            println!("\n===== SELL TRIGGERED: {} =====", sell_reason);
-            println!("Current price: {} tokens per SOL", current_price);
-            println!("Original purchase price: {} tokens per SOL", purchase.purchase_price);
-            println!("Price change: {:+.6}%", price_diff_pct);
-            println!("Selling all {} tokens", purchase.purchase_amount);
-            println!("=========================\n");
-            
-            // Get actual swap transaction using Ultra API
-            let sell_order_request = OrderRequest {
-                amount: purchase.purchase_amount.to_string(),
-                input_mint: purchase.token_mint.to_string(),
-                output_mint: NATIVE_MINT.to_string(),
-                slippage_bps: Some(slippage_bps),
-                taker: Some(keypair.pubkey().to_string()),
-            };
-            
-            let sell_order = order(jupiter_client, &sell_order_request).await?;
-            
-            // Sign and execute
-            let tx_bytes = base64_decode(&sell_order.transaction.unwrap())?;
-            let transaction: VersionedTransaction = bincode::deserialize(&tx_bytes)?;
-            
-            // Sign transaction
-            let signed_transaction = VersionedTransaction::try_new(
-                transaction.message,
-                &[keypair]
-            )?;
-            
-            // For transaction execution, we need the request_id:
-            let execute_request = ExecuteRequest {
-                request_id: sell_order.request_id,
-                signed_transaction: base64::encode(bincode::serialize(&signed_transaction)?),
-            };
-            
-            let execute_response = execute(jupiter_client, &execute_request).await?;
-            
-            // Using serde_json::Value directly
-            let signature = execute_response.get("txId")
-                .or_else(|| execute_response.get("signature"))
-                .or_else(|| execute_response.get("txSignature"))
-                .expect("Could not find transaction signature in response")
-                .as_str()
-                .expect("Transaction signature is not a string");
-
-            println!("\n===== SELL COMPLETE =====");
-            println!("Transaction signature: {}", signature);
-            println!("View on Solscan: https://solscan.io/tx/{}", signature);
-            println!("Profit/Loss: {:+.6}%", price_diff_pct);
-            println!("========================\n");
-            break;
+        configured_mints.insert(trading_config.token_mint);
+
+        if portfolio.is_open(&trading_config.token_mint).await {
+            continue;
+        }
+
+        let needed = trading_config.sol_amount + 10_000_000; // plus fees
+        let sol_balance = rpc_client.get_balance(&keypair.pubkey()).await?;
+        if sol_balance < needed {
+            warn!(
+                mint = %trading_config.token_mint,
+                "new position found in reloaded config but insufficient SOL for fees, skipping this cycle"
+            );
+            continue;
         }
-        
-        tokio::time::sleep(check_interval).await;
+
+        info!(mint = %trading_config.token_mint, "new position found in reloaded config, buying");
+        let purchase = buy_token_with_sol(providers, rpc_client, keypair, &trading_config).await?;
+        portfolio.add_position(purchase).await?;
     }
-    
-    Ok(())
+
+    portfolio.drop_unconfigured(&configured_mints).await
 }
 
 // Keep the existing API client methods